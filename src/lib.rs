@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 #[derive(Clone, Debug)]
 pub struct Plugin {}
 
@@ -31,10 +34,304 @@ macro_rules! get_plugin {
 
 #[macro_export]
 macro_rules! get_type_from_plugin {
-    ($name:ident, $plugins:expr, $api:ty) => {
+    ($name:ident, $plugins:expr, $api:path) => {
         $plugins
             .get(stringify!($name))
-            .map(|p| &p.instance as $name::&$api)
+            .map(|p| &p.instance as &dyn $api)
             .expect(&format!("Plugin {} not found", stringify!($name)))
     };
-}
\ No newline at end of file
+}
+
+/// Limits how often log lines sharing a template reach a sink.
+///
+/// At most `limit` lines per template are forwarded in each `interval`. When a
+/// window that dropped lines ends, a single summary of how many were suppressed
+/// is forwarded before the next line with that template.
+///
+/// Summaries are only produced by a later line with the same template or by
+/// [`flush`](Self::flush). A template that bursts and then goes quiet is not
+/// reported until one of those happens, so call `flush` regularly (e.g. once
+/// per tick), not just at shutdown, for summaries to show up on time.
+///
+/// `template` should be the unformatted message, not the formatted line. Windows
+/// that have been idle for a full `interval` with nothing suppressed are pruned
+/// whenever a new template is seen; windows still holding a summary are kept
+/// until it is forwarded or flushed.
+///
+/// ```
+/// use horizon_plugin_api::LogThrottle;
+/// use std::time::{Duration, Instant};
+///
+/// let mut throttle = LogThrottle::new(2, Duration::from_secs(1));
+/// let mut lines = Vec::new();
+/// let start = Instant::now();
+///
+/// for i in 0..5 {
+///     throttle.log_at(start, "tick", &format!("tick {i}"), |l| lines.push(l.to_string()));
+/// }
+/// throttle.log_at(start + Duration::from_secs(1), "tick", "tick 5", |l| lines.push(l.to_string()));
+///
+/// assert_eq!(lines, [
+///     "tick 0",
+///     "tick 1",
+///     "suppressed 3 messages matching \"tick\"",
+///     "tick 5",
+/// ]);
+/// ```
+///
+/// Each template is throttled independently:
+///
+/// ```
+/// use horizon_plugin_api::LogThrottle;
+/// use std::time::{Duration, Instant};
+///
+/// let mut throttle = LogThrottle::new(1, Duration::from_secs(1));
+/// let mut lines = Vec::new();
+/// let start = Instant::now();
+///
+/// throttle.log_at(start, "join", "player joined", |l| lines.push(l.to_string()));
+/// throttle.log_at(start, "join", "player joined", |l| lines.push(l.to_string()));
+/// throttle.log_at(start, "move", "player moved", |l| lines.push(l.to_string()));
+/// throttle.log_at(start + Duration::from_secs(1), "move", "player moved", |l| lines.push(l.to_string()));
+///
+/// assert_eq!(lines, ["player joined", "player moved", "player moved"]);
+/// ```
+#[derive(Debug)]
+pub struct LogThrottle {
+    limit: usize,
+    interval: Duration,
+    windows: HashMap<String, ThrottleWindow>,
+}
+
+#[derive(Debug)]
+struct ThrottleWindow {
+    started: Instant,
+    emitted: usize,
+    suppressed: usize,
+}
+
+impl ThrottleWindow {
+    fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            emitted: 0,
+            suppressed: 0,
+        }
+    }
+
+    fn is_idle(&self, now: Instant, interval: Duration) -> bool {
+        self.suppressed == 0 && now.saturating_duration_since(self.started) >= interval
+    }
+
+    fn record(
+        &mut self,
+        now: Instant,
+        limit: usize,
+        interval: Duration,
+        template: &str,
+        message: &str,
+        sink: &mut impl FnMut(&str),
+    ) {
+        if now.saturating_duration_since(self.started) >= interval {
+            if self.suppressed > 0 {
+                sink(&suppressed_summary(template, self.suppressed));
+            }
+            *self = Self::new(now);
+        }
+
+        if self.emitted < limit {
+            self.emitted += 1;
+            sink(message);
+        } else {
+            self.suppressed += 1;
+        }
+    }
+}
+
+impl LogThrottle {
+    /// Creates a throttle that forwards at most `limit` lines per template in
+    /// each `interval`. A `limit` of zero forwards only the summaries.
+    ///
+    /// ```
+    /// use horizon_plugin_api::LogThrottle;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut throttle = LogThrottle::new(0, Duration::from_secs(1));
+    /// let mut lines = Vec::new();
+    /// let start = Instant::now();
+    ///
+    /// throttle.log_at(start, "tick", "tick 0", |l| lines.push(l.to_string()));
+    /// throttle.log_at(start, "tick", "tick 1", |l| lines.push(l.to_string()));
+    /// assert!(lines.is_empty());
+    ///
+    /// throttle.log_at(start + Duration::from_secs(1), "tick", "tick 2", |l| lines.push(l.to_string()));
+    /// assert_eq!(lines, ["suppressed 2 messages matching \"tick\""]);
+    /// ```
+    pub fn new(limit: usize, interval: Duration) -> Self {
+        Self {
+            limit,
+            interval,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Same as [`log_at`](Self::log_at), using `Instant::now()` as the time.
+    pub fn log(&mut self, template: &str, message: &str, sink: impl FnMut(&str)) {
+        self.log_at(Instant::now(), template, message, sink);
+    }
+
+    /// Forwards `message` to `sink` unless `template` has used up its `limit`
+    /// for the current window. `now` is the current time, so callers can drive
+    /// the throttle from their own clock.
+    pub fn log_at(
+        &mut self,
+        now: Instant,
+        template: &str,
+        message: &str,
+        mut sink: impl FnMut(&str),
+    ) {
+        let (limit, interval) = (self.limit, self.interval);
+
+        match self.windows.get_mut(template) {
+            Some(window) => window.record(now, limit, interval, template, message, &mut sink),
+            None => {
+                self.windows
+                    .retain(|_, window| !window.is_idle(now, interval));
+
+                let mut window = ThrottleWindow::new(now);
+                window.record(now, limit, interval, template, message, &mut sink);
+                self.windows.insert(template.to_owned(), window);
+            }
+        }
+    }
+
+    /// Forwards pending summaries, ordered by template, without waiting for
+    /// the windows to end, e.g. when the plugin shuts down.
+    ///
+    /// ```
+    /// use horizon_plugin_api::LogThrottle;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut throttle = LogThrottle::new(1, Duration::from_secs(1));
+    /// let mut lines = Vec::new();
+    /// let start = Instant::now();
+    ///
+    /// for _ in 0..3 {
+    ///     throttle.log_at(start, "move", "player moved", |l| lines.push(l.to_string()));
+    ///     throttle.log_at(start, "join", "player joined", |l| lines.push(l.to_string()));
+    /// }
+    /// throttle.flush(|l| lines.push(l.to_string()));
+    ///
+    /// // Flushed counts are not reported again when the window rolls over.
+    /// throttle.log_at(start + Duration::from_secs(1), "move", "player moved", |l| lines.push(l.to_string()));
+    ///
+    /// assert_eq!(lines, [
+    ///     "player moved",
+    ///     "player joined",
+    ///     "suppressed 2 messages matching \"join\"",
+    ///     "suppressed 2 messages matching \"move\"",
+    ///     "player moved",
+    /// ]);
+    /// ```
+    pub fn flush(&mut self, mut sink: impl FnMut(&str)) {
+        let mut pending: Vec<(&str, usize)> = self
+            .windows
+            .iter_mut()
+            .filter(|(_, window)| window.suppressed > 0)
+            .map(|(template, window)| (template.as_str(), std::mem::take(&mut window.suppressed)))
+            .collect();
+        pending.sort_unstable();
+
+        for (template, count) in pending {
+            sink(&suppressed_summary(template, count));
+        }
+    }
+}
+
+fn suppressed_summary(template: &str, count: usize) -> String {
+    let noun = if count == 1 { "message" } else { "messages" };
+    format!("suppressed {} {} matching \"{}\"", count, noun, template)
+}
+
+/// Re-exports the types most plugins need, so a plugin can start with
+/// `use horizon_plugin_api::prelude::*;`.
+///
+/// ```
+/// use horizon_plugin_api::prelude::*;
+///
+/// mod greeter {
+///     pub trait PluginAPI {
+///         fn greet(&self) -> &'static str;
+///     }
+/// }
+///
+/// impl greeter::PluginAPI for Plugin {
+///     fn greet(&self) -> &'static str {
+///         "hello"
+///     }
+/// }
+///
+/// let mut plugins = std::collections::HashMap::new();
+/// plugins.insert("greeter", LoadedPlugin { instance: Plugin {} });
+///
+/// assert_eq!(get_plugin!(greeter, plugins).greet(), "hello");
+/// assert_eq!(get_type_from_plugin!(greeter, plugins, greeter::PluginAPI).greet(), "hello");
+/// ```
+pub mod prelude {
+    pub use crate::{get_plugin, get_type_from_plugin};
+    pub use crate::{LoadedPlugin, LogThrottle, Plugin, Pluginstate, Version};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(lines: &mut Vec<String>) -> impl FnMut(&str) + '_ {
+        |line| lines.push(line.to_string())
+    }
+
+    #[test]
+    fn idle_windows_are_pruned_when_a_new_template_arrives() {
+        let mut throttle = LogThrottle::new(1, Duration::from_secs(1));
+        let mut lines = Vec::new();
+        let start = Instant::now();
+
+        throttle.log_at(start, "join", "player joined", collect(&mut lines));
+        throttle.log_at(start, "move", "player moved", collect(&mut lines));
+        assert_eq!(throttle.windows.len(), 2);
+
+        let later = start + Duration::from_secs(1);
+        throttle.log_at(later, "chat", "player chatted", collect(&mut lines));
+
+        assert_eq!(throttle.windows.len(), 1);
+        assert!(throttle.windows.contains_key("chat"));
+        assert_eq!(lines, ["player joined", "player moved", "player chatted"]);
+    }
+
+    #[test]
+    fn windows_holding_a_summary_are_kept() {
+        let mut throttle = LogThrottle::new(1, Duration::from_secs(1));
+        let mut lines = Vec::new();
+        let start = Instant::now();
+
+        throttle.log_at(start, "join", "player joined", collect(&mut lines));
+        throttle.log_at(start, "join", "player joined", collect(&mut lines));
+
+        let later = start + Duration::from_secs(1);
+        throttle.log_at(later, "chat", "player chatted", collect(&mut lines));
+
+        assert_eq!(throttle.windows["join"].suppressed, 1);
+        assert_eq!(throttle.windows.len(), 2);
+
+        throttle.log_at(later, "join", "player joined", collect(&mut lines));
+        assert_eq!(
+            lines,
+            [
+                "player joined",
+                "player chatted",
+                "suppressed 1 message matching \"join\"",
+                "player joined",
+            ]
+        );
+    }
+}